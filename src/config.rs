@@ -0,0 +1,152 @@
+//! TOML config file support: multiple named relying-party profiles instead
+//! of the single endpoint the server originally took as `args[1]`.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// One named relying-party backend: its endpoint, optional credentials,
+/// timeout, and TLS settings.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProfileConfig {
+    pub endpoint: String,
+    #[serde(default)]
+    pub bearer_token: Option<String>,
+    #[serde(default)]
+    pub header_name: Option<String>,
+    #[serde(default)]
+    pub header_value: Option<String>,
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    #[serde(default)]
+    pub accept_invalid_certs: bool,
+}
+
+impl ProfileConfig {
+    pub fn new(endpoint: String) -> Self {
+        Self {
+            endpoint,
+            bearer_token: None,
+            header_name: None,
+            header_value: None,
+            timeout_secs: None,
+            accept_invalid_certs: false,
+        }
+    }
+}
+
+/// A set of named profiles plus which one tools hit when a call doesn't
+/// specify a `profile` argument.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FileConfig {
+    pub default_profile: String,
+    pub profiles: HashMap<String, ProfileConfig>,
+}
+
+impl FileConfig {
+    /// Builds a config with a single named profile, e.g. from legacy
+    /// `args[1]`-style startup flags rather than a TOML file.
+    pub fn single(name: String, profile: ProfileConfig) -> Self {
+        let mut profiles = HashMap::new();
+        profiles.insert(name.clone(), profile);
+        Self {
+            default_profile: name,
+            profiles,
+        }
+    }
+
+    /// Conventional config locations, checked in order.
+    fn default_paths() -> Vec<String> {
+        let mut paths = vec!["./rpki-mcp.toml".to_string()];
+        if let Ok(home) = std::env::var("HOME") {
+            paths.push(format!("{home}/.config/rpki-mcp/config.toml"));
+        }
+        paths
+    }
+
+    /// Loads the config from an explicitly-named file (`--config` or
+    /// `RPKI_MCP_CONFIG`).
+    pub fn load(path: &str) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|err| format!("Config file '{path}' not found: {err}"))?;
+        Self::parse(&contents, path)
+    }
+
+    /// Tries the conventional config locations in order. Returns `Ok(None)`
+    /// if none exist, so the caller can fall back to single-profile startup
+    /// flags.
+    pub fn load_ambient() -> Result<Option<Self>, String> {
+        for candidate in Self::default_paths() {
+            if let Ok(contents) = std::fs::read_to_string(&candidate) {
+                return Self::parse(&contents, &candidate).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// The first conventional config location that exists on disk, without
+    /// parsing it. Used to warn when an explicitly-supplied endpoint is
+    /// about to take priority over a file a user might not expect to be
+    /// ignored.
+    pub fn ambient_path() -> Option<String> {
+        Self::default_paths()
+            .into_iter()
+            .find(|path| std::path::Path::new(path).is_file())
+    }
+
+    fn parse(contents: &str, source: &str) -> Result<Self, String> {
+        let config: FileConfig =
+            toml::from_str(contents).map_err(|err| format!("Invalid config file '{source}': {err}"))?;
+        if !config.profiles.contains_key(&config.default_profile) {
+            return Err(format!(
+                "default_profile '{}' is not a defined profile in '{source}'",
+                config.default_profile
+            ));
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_accepts_valid_toml() {
+        let toml = r#"
+            default_profile = "prod"
+
+            [profiles.prod]
+            endpoint = "https://rp.example.com"
+        "#;
+        let config = FileConfig::parse(toml, "test.toml").unwrap();
+        assert_eq!(config.default_profile, "prod");
+        assert_eq!(config.profiles["prod"].endpoint, "https://rp.example.com");
+    }
+
+    #[test]
+    fn parse_rejects_unknown_default_profile() {
+        let toml = r#"
+            default_profile = "missing"
+
+            [profiles.prod]
+            endpoint = "https://rp.example.com"
+        "#;
+        assert!(FileConfig::parse(toml, "test.toml").is_err());
+    }
+
+    #[test]
+    fn parse_rejects_invalid_toml() {
+        assert!(FileConfig::parse("not valid toml {{{", "test.toml").is_err());
+    }
+
+    #[test]
+    fn single_builds_one_profile_config() {
+        let config = FileConfig::single(
+            "default".to_string(),
+            ProfileConfig::new("https://rp.example.com".to_string()),
+        );
+        assert_eq!(config.default_profile, "default");
+        assert_eq!(config.profiles.len(), 1);
+        assert_eq!(config.profiles["default"].endpoint, "https://rp.example.com");
+    }
+}
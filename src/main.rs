@@ -4,35 +4,132 @@ use rmcp::{
     model::*,
     service::ServerInitializeError,
     tool, tool_handler, tool_router,
-    transport::stdio,
+    transport::{
+        sse_server::SseServer,
+        stdio,
+        streamable_http_server::{StreamableHttpService, session::local::LocalSessionManager},
+    },
 };
 use rpki::repository::Roa;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::convert::Infallible;
 use std::{
     borrow::Cow,
     env, error, fmt,
     fs::{self, OpenOptions},
     io::Error as IoError,
+    time::Duration,
 };
 use tokio::task::JoinError;
 
+mod config;
+mod metrics;
+mod vrp;
+
 trait IntoMcpError<T> {
     fn into_mcp_error(self) -> Result<T, McpError>;
 }
 
+/// Builds an `McpError` carrying a structured `{kind, source}` data payload.
+fn mcp_error(code: ErrorCode, kind: &str, message: String, source: impl fmt::Display) -> McpError {
+    McpError {
+        code,
+        message: Cow::from(message),
+        data: Some(json!({ "kind": kind, "source": source.to_string() })),
+    }
+}
+
+/// Builds an `McpError` for bad caller input (`INVALID_PARAMS`).
+fn invalid_params(kind: &str, message: String) -> McpError {
+    McpError {
+        code: ErrorCode::INVALID_PARAMS,
+        data: Some(json!({ "kind": kind })),
+        message: Cow::from(message),
+    }
+}
+
+/// Accepts both bare digits (`64496`) and the conventional `AS` prefix
+/// (`AS64496`).
+fn parse_asn(raw: &str) -> Result<u32, String> {
+    let trimmed = raw.trim();
+    let digits = trimmed
+        .strip_prefix("AS")
+        .or_else(|| trimmed.strip_prefix("as"))
+        .unwrap_or(trimmed);
+    digits
+        .parse::<u32>()
+        .map_err(|_| format!("'{raw}' is not a valid ASN"))
+}
+
+fn parse_prefix(raw: &str) -> Result<(std::net::IpAddr, u8), String> {
+    let (addr, len) = raw
+        .split_once('/')
+        .ok_or_else(|| format!("'{raw}' is not a valid prefix, expected address/length"))?;
+    let addr: std::net::IpAddr = addr
+        .parse()
+        .map_err(|_| format!("'{raw}' is not a valid prefix: invalid address"))?;
+    let len: u8 = len
+        .parse()
+        .map_err(|_| format!("'{raw}' is not a valid prefix: invalid length"))?;
+    let max_len = if addr.is_ipv4() { 32 } else { 128 };
+    if len > max_len {
+        return Err(format!(
+            "'{raw}' is not a valid prefix: length {len} exceeds {max_len}"
+        ));
+    }
+    Ok((addr, len))
+}
+
+/// Reads a `Retry-After` header (seconds form only), if present.
+fn retry_after_delay(res: &reqwest::Response) -> Option<Duration> {
+    let value = res.headers().get(reqwest::header::RETRY_AFTER)?;
+    let secs: u64 = value.to_str().ok()?.trim().parse().ok()?;
+    Some(Duration::from_secs(secs))
+}
+
+/// Exponential backoff capped at [`MAX_RETRY_DELAY`], plus jitter so
+/// concurrent callers don't retry in lockstep.
+fn backoff_delay(attempt: u32) -> Duration {
+    let exponent = attempt.saturating_sub(1).min(16);
+    let scaled = BASE_RETRY_DELAY.saturating_mul(1u32 << exponent);
+    let capped = scaled.min(MAX_RETRY_DELAY);
+    let jitter = Duration::from_millis(rand::random::<u64>() % 50);
+    capped + jitter
+}
+
+/// Runs `f`, recording a request counter, a latency histogram, and (on
+/// failure) an error counter broken down by JSON-RPC error code — the
+/// bookkeeping every tool call does around its own logic.
+async fn instrument<F, Fut>(tool: &'static str, f: F) -> Result<CallToolResult, McpError>
+where
+    F: FnOnce() -> Fut,
+    Fut: std::future::Future<Output = Result<CallToolResult, McpError>>,
+{
+    metrics::record_request(tool);
+    let start = std::time::Instant::now();
+    let result = f().await;
+    metrics::record_latency(tool, start.elapsed());
+    if let Err(err) = &result {
+        metrics::record_error(tool, err.code.0);
+    }
+    result
+}
+
 impl<T> IntoMcpError<T> for Result<T, reqwest::Error> {
     fn into_mcp_error(self) -> Result<T, McpError> {
         self.map_err(|err| {
             tracing::error!("Request failed: {:?}", err);
-            McpError {
-                code: err
-                    .status()
-                    .map(|s| ErrorCode(s.as_u16() as i32))
-                    .unwrap_or(ErrorCode(-1)),
-                message: Cow::from(format!("Request failed: {err}")),
-                data: None,
-            }
+            // Prefer the upstream HTTP status code when there is one.
+            let code = err
+                .status()
+                .map(|s| ErrorCode(s.as_u16() as i32))
+                .unwrap_or(if err.is_decode() {
+                    ErrorCode::PARSE_ERROR
+                } else {
+                    ErrorCode::INTERNAL_ERROR
+                });
+            mcp_error(code, "http_request", format!("Request failed: {err}"), err)
         })
     }
 }
@@ -41,11 +138,12 @@ impl<T> IntoMcpError<T> for Result<T, serde_json::Error> {
     fn into_mcp_error(self) -> Result<T, McpError> {
         self.map_err(|err| {
             tracing::error!("Failed to serialize: {:?}", err);
-            McpError {
-                code: ErrorCode(-1),
-                message: Cow::from(format!("Failed to serialize response: {err}")),
-                data: None,
-            }
+            mcp_error(
+                ErrorCode::PARSE_ERROR,
+                "serialization",
+                format!("Failed to serialize response: {err}"),
+                err,
+            )
         })
     }
 }
@@ -54,11 +152,13 @@ impl<T> IntoMcpError<T> for Result<T, std::io::Error> {
     fn into_mcp_error(self) -> Result<T, McpError> {
         self.map_err(|err| {
             tracing::error!("Failed to read file: {:?}", err);
-            McpError {
-                code: ErrorCode(-1),
-                message: Cow::from(format!("Failed to read file: {err}")),
-                data: None,
-            }
+            // A missing path is the caller's fault; anything else is ours.
+            let code = if err.kind() == std::io::ErrorKind::NotFound {
+                ErrorCode::INVALID_PARAMS
+            } else {
+                ErrorCode::INTERNAL_ERROR
+            };
+            mcp_error(code, "file_io", format!("Failed to read file: {err}"), err)
         })
     }
 }
@@ -67,11 +167,12 @@ impl<T> IntoMcpError<T> for Result<T, rpki::dep::bcder::decode::DecodeError<Infa
     fn into_mcp_error(self) -> Result<T, McpError> {
         self.map_err(|err| {
             tracing::error!("Failed to decode file: {:?}", err);
-            McpError {
-                code: ErrorCode(-1),
-                message: Cow::from(format!("Failed to decode file: {err}")),
-                data: None,
-            }
+            mcp_error(
+                ErrorCode::PARSE_ERROR,
+                "roa_decode",
+                format!("Failed to decode file: {err}"),
+                err,
+            )
         })
     }
 }
@@ -142,6 +243,23 @@ struct ValidityArgs {
     asn: String,
     #[schemars(description = "The IP address prefix to validate (e.g., 192.0.2.0/24)")]
     prefix: String,
+    #[serde(default)]
+    #[schemars(
+        description = "Name of the configured relying-party profile to query; defaults to the server's configured default profile"
+    )]
+    profile: Option<String>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+struct OfflineValidityArgs {
+    #[schemars(description = "The Autonomous System Number (ASN) to validate")]
+    asn: String,
+    #[schemars(description = "The IP address prefix to validate (e.g., 192.0.2.0/24)")]
+    prefix: String,
+    #[schemars(
+        description = "Path to a local VRP dump (JSON, as produced by the `roas` tool, or CSV in ASN,Prefix,MaxLength form) to validate against"
+    )]
+    vrp_file: String,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -170,6 +288,20 @@ struct FetchedRoas {
 struct RoasArgs {
     #[schemars(description = "The Autonomous System Number (ASN) to retrieve ROAs for")]
     asn: String,
+    #[serde(default)]
+    #[schemars(
+        description = "Name of the configured relying-party profile to query; defaults to the server's configured default profile"
+    )]
+    profile: Option<String>,
+}
+
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+struct StatusArgs {
+    #[serde(default)]
+    #[schemars(
+        description = "Name of the configured relying-party profile to query; defaults to the server's configured default profile"
+    )]
+    profile: Option<String>,
 }
 
 #[derive(Debug, Deserialize, schemars::JsonSchema)]
@@ -177,8 +309,132 @@ struct ParseRoaFileArgs {
     #[schemars(description = "The file path to the ROA file to parse")]
     path: String,
 }
-struct RPKITool {
+/// Request timeout applied to every outbound call to the relying party.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Retry attempts for a transiently-failing request, on top of the first.
+const MAX_RETRY_ATTEMPTS: u32 = 3;
+const BASE_RETRY_DELAY: Duration = Duration::from_millis(200);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// Cache of loaded offline VRP indexes, keyed by the file path they were
+/// built from, shared across clones of `RPKITool` so repeated queries reuse
+/// the same in-memory index instead of re-parsing the dump.
+type VrpCache = std::sync::Arc<tokio::sync::Mutex<std::collections::HashMap<String, std::sync::Arc<vrp::VrpIndex>>>>;
+
+/// One configured relying-party backend: its endpoint, HTTP client, and
+/// credential.
+struct Profile {
     endpoint: String,
+    http_client: reqwest::Client,
+    auth: Option<AuthConfig>,
+}
+
+impl Profile {
+    fn new(
+        endpoint: String,
+        auth: Option<AuthConfig>,
+        timeout: Duration,
+        accept_invalid_certs: bool,
+    ) -> Result<Self, String> {
+        if !endpoint.starts_with("http://") && !endpoint.starts_with("https://") {
+            return Err(format!(
+                "Endpoint '{endpoint}' must start with http:// or https://"
+            ));
+        }
+        if endpoint.trim().is_empty() {
+            return Err("Endpoint cannot be empty".to_string());
+        }
+        // A token sent to an `http://` endpoint is visible on the wire.
+        if auth.is_some() && endpoint.starts_with("http://") {
+            return Err(format!(
+                "Refusing to send credentials to plaintext endpoint '{endpoint}'; use https://"
+            ));
+        }
+
+        let http_client = reqwest::Client::builder()
+            .timeout(timeout)
+            .danger_accept_invalid_certs(accept_invalid_certs)
+            .build()
+            .map_err(|err| format!("Failed to build HTTP client for '{endpoint}': {err}"))?;
+
+        Ok(Self {
+            endpoint,
+            http_client,
+            auth,
+        })
+    }
+
+    fn request(&self, url: &str) -> reqwest::RequestBuilder {
+        let builder = self.http_client.get(url);
+        match &self.auth {
+            Some(AuthConfig::Bearer(token)) => builder.bearer_auth(token),
+            Some(AuthConfig::Header { name, value }) => builder.header(name, value),
+            None => builder,
+        }
+    }
+
+    /// Retries connection errors and 429/5xx responses up to
+    /// [`MAX_RETRY_ATTEMPTS`] times, honoring `Retry-After` when present.
+    async fn get_with_retry(&self, url: &str) -> Result<reqwest::Response, McpError> {
+        let mut attempt: u32 = 0;
+        loop {
+            attempt += 1;
+            match self.request(url).send().await {
+                Ok(res) => {
+                    let status = res.status();
+                    let retryable = status.as_u16() == 429 || status.is_server_error();
+                    if !retryable || attempt > MAX_RETRY_ATTEMPTS {
+                        return Ok(res);
+                    }
+                    let delay = retry_after_delay(&res).unwrap_or_else(|| backoff_delay(attempt));
+                    tracing::warn!(
+                        "Retrying {url} in {delay:?} (attempt {attempt}/{MAX_RETRY_ATTEMPTS}, status {status})"
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                Err(err) => {
+                    if attempt > MAX_RETRY_ATTEMPTS || !(err.is_connect() || err.is_timeout()) {
+                        return Err(err).into_mcp_error();
+                    }
+                    let delay = backoff_delay(attempt);
+                    tracing::warn!(
+                        "Retrying {url} in {delay:?} (attempt {attempt}/{MAX_RETRY_ATTEMPTS}): {err}"
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    }
+}
+
+fn build_profile(name: &str, config: &config::ProfileConfig) -> Result<Profile, String> {
+    let auth = match (&config.bearer_token, &config.header_name, &config.header_value) {
+        (Some(token), _, _) => Some(AuthConfig::Bearer(token.clone())),
+        (None, Some(header_name), Some(header_value)) => Some(AuthConfig::Header {
+            name: header_name.clone(),
+            value: header_value.clone(),
+        }),
+        (None, None, None) => None,
+        _ => return Err("header_name and header_value must both be set".to_string()),
+    };
+    let timeout = config
+        .timeout_secs
+        .map(Duration::from_secs)
+        .unwrap_or(REQUEST_TIMEOUT);
+    Profile::new(
+        config.endpoint.clone(),
+        auth,
+        timeout,
+        config.accept_invalid_certs,
+    )
+    .map_err(|err| format!("profile '{name}': {err}"))
+}
+
+#[derive(Clone)]
+struct RPKITool {
+    profiles: std::sync::Arc<std::collections::HashMap<String, Profile>>,
+    default_profile: String,
+    vrp_cache: VrpCache,
     tool_router: ToolRouter<RPKITool>,
 }
 
@@ -199,67 +455,133 @@ impl RPKITool {
         Ok(json_value)
     }
 
-    fn new(endpoint: String) -> Result<Self, String> {
-        // Basic validation: check if it starts with http:// or https://
-        if !endpoint.starts_with("http://") && !endpoint.starts_with("https://") {
-            return Err("Endpoint must start with http:// or https://".to_string());
+    fn new(config: config::FileConfig) -> Result<Self, String> {
+        if config.profiles.is_empty() {
+            return Err("No relying-party profiles configured".to_string());
+        }
+        if !config.profiles.contains_key(&config.default_profile) {
+            return Err(format!(
+                "default_profile '{}' is not a defined profile",
+                config.default_profile
+            ));
         }
 
-        // Try to validate by creating a reqwest URL (reqwest will validate it)
-        // We can use reqwest's internal validation by attempting to use it
-        // Since reqwest::get accepts &str, we'll validate by checking basic URL structure
-        if endpoint.trim().is_empty() {
-            return Err("Endpoint cannot be empty".to_string());
+        let mut profiles = std::collections::HashMap::new();
+        for (name, profile_config) in &config.profiles {
+            profiles.insert(name.clone(), build_profile(name, profile_config)?);
         }
 
         Ok(Self {
-            endpoint,
+            profiles: std::sync::Arc::new(profiles),
+            default_profile: config.default_profile,
+            vrp_cache: std::sync::Arc::new(tokio::sync::Mutex::new(std::collections::HashMap::new())),
             tool_router: Self::tool_router(),
         })
     }
 
-    /// Generic helper to fetch JSON from an endpoint and return it as a CallToolResult
-    async fn fetch_json_response<T>(url: String) -> Result<CallToolResult, McpError>
+    /// Resolves `name` (or the configured default, if `None`) to a profile.
+    fn profile(&self, name: Option<&str>) -> Result<&Profile, McpError> {
+        let key = name.unwrap_or(&self.default_profile);
+        self.profiles
+            .get(key)
+            .ok_or_else(|| invalid_params("profile", format!("Unknown profile '{key}'")))
+    }
+
+    /// Returns the VRP index for `path`, loading and caching it on first use.
+    async fn vrp_index(&self, path: &str) -> Result<std::sync::Arc<vrp::VrpIndex>, McpError> {
+        let mut cache = self.vrp_cache.lock().await;
+        if let Some(index) = cache.get(path) {
+            return Ok(index.clone());
+        }
+        let index = std::sync::Arc::new(
+            vrp::VrpIndex::load(path).map_err(|msg| invalid_params("vrp_file", msg))?,
+        );
+        cache.insert(path.to_string(), index.clone());
+        Ok(index)
+    }
+
+    /// Fetches JSON from a profile's endpoint and returns it as a
+    /// `CallToolResult`, instrumented under `tool` (the public tool name).
+    async fn fetch_json_response<T>(
+        profile: &Profile,
+        tool: &'static str,
+        url: String,
+    ) -> Result<CallToolResult, McpError>
     where
         T: for<'de> Deserialize<'de> + Serialize,
     {
-        let res = reqwest::get(&url).await.into_mcp_error()?;
+        instrument(tool, || async {
+            let res = profile.get_with_retry(&url).await?;
 
-        if !res.status().is_success() {
-            let status_code = res.status().as_u16() as i32;
-            let error_text = res
-                .text()
-                .await
-                .unwrap_or_else(|_| "Unknown error".to_string());
-            tracing::error!("HTTP error {}: {}", status_code, error_text);
-            return Err(McpError {
-                code: ErrorCode(status_code),
-                message: Cow::from(error_text),
-                data: None,
-            });
-        }
+            if !res.status().is_success() {
+                let status_code = res.status().as_u16() as i32;
+                let error_text = res
+                    .text()
+                    .await
+                    .unwrap_or_else(|_| "Unknown error".to_string());
+                tracing::error!("HTTP error {}: {}", status_code, error_text);
+                return Err(mcp_error(
+                    ErrorCode(status_code),
+                    "http_status",
+                    error_text,
+                    format!("HTTP {status_code}"),
+                ));
+            }
 
-        let data = res.json::<T>().await.into_mcp_error()?;
+            let data = res.json::<T>().await.into_mcp_error()?;
 
-        let json_value = RPKITool::to_json(data)?;
+            let json_value = RPKITool::to_json(data)?;
 
-        Ok(CallToolResult::structured(json_value))
+            Ok(CallToolResult::structured(json_value))
+        })
+        .await
     }
 
     #[tool(description = "Status of the RPKI relying party")]
-    async fn status(&self) -> Result<CallToolResult, McpError> {
-        Self::fetch_json_response::<StatusResponse>(format!("{}/api/v1/status", self.endpoint))
-            .await
+    async fn status(&self, args: Parameters<StatusArgs>) -> Result<CallToolResult, McpError> {
+        let profile = self.profile(args.0.profile.as_deref())?;
+        Self::fetch_json_response::<StatusResponse>(
+            profile,
+            "status",
+            format!("{}/api/v1/status", profile.endpoint),
+        )
+        .await
     }
 
     #[tool(
         description = "Returns a JSON object indicating whether a route announcement identified by its origin Autonomous System Number (ASN) and IP address prefix is RPKI valid, invalid, or not found. The response also includes the complete set of Validated ROA Payloads (VRPs) that determined this outcome"
     )]
     async fn validity(&self, args: Parameters<ValidityArgs>) -> Result<CallToolResult, McpError> {
-        Self::fetch_json_response::<ValidityResponse>(format!(
-            "{}/api/v1/validity/{}/{}",
-            self.endpoint, args.0.asn, args.0.prefix
-        ))
+        parse_asn(&args.0.asn).map_err(|msg| invalid_params("asn", msg))?;
+        parse_prefix(&args.0.prefix).map_err(|msg| invalid_params("prefix", msg))?;
+        let profile = self.profile(args.0.profile.as_deref())?;
+
+        Self::fetch_json_response::<ValidityResponse>(
+            profile,
+            "validity",
+            format!(
+                "{}/api/v1/validity/{}/{}",
+                profile.endpoint, args.0.asn, args.0.prefix
+            ),
+        )
+        .await
+    }
+
+    #[tool(
+        description = "Validates a route announcement (origin ASN + IP prefix) against a Validated ROA Payload (VRP) set loaded from a local file (JSON or CSV), without contacting a remote relying party. Useful for air-gapped or batch validation. The VRP file is parsed and indexed once and the index is reused for subsequent queries against the same file. Returns the same shape as `validity`"
+    )]
+    async fn validity_offline(
+        &self,
+        args: Parameters<OfflineValidityArgs>,
+    ) -> Result<CallToolResult, McpError> {
+        instrument("validity_offline", || async {
+            let index = self.vrp_index(&args.0.vrp_file).await?;
+            let response = index
+                .query(&args.0.asn, &args.0.prefix)
+                .map_err(|msg| invalid_params("asn_or_prefix", msg))?;
+            let json_value = RPKITool::to_json(response)?;
+            Ok(CallToolResult::structured(json_value))
+        })
         .await
     }
 
@@ -267,10 +589,14 @@ impl RPKITool {
         description = "Retrieves all Route Origin Authorizations (ROAs) for a given Autonomous System Number (ASN). Returns a JSON object containing metadata and a list of ROAs associated with the specified ASN"
     )]
     async fn roas(&self, args: Parameters<RoasArgs>) -> Result<CallToolResult, McpError> {
-        Self::fetch_json_response::<FetchedRoas>(format!(
-            "{}/json?select-asn={}",
-            self.endpoint, args.0.asn
-        ))
+        parse_asn(&args.0.asn).map_err(|msg| invalid_params("asn", msg))?;
+        let profile = self.profile(args.0.profile.as_deref())?;
+
+        Self::fetch_json_response::<FetchedRoas>(
+            profile,
+            "roas",
+            format!("{}/json?select-asn={}", profile.endpoint, args.0.asn),
+        )
         .await
     }
 
@@ -281,33 +607,36 @@ impl RPKITool {
         &self,
         path: Parameters<ParseRoaFileArgs>,
     ) -> Result<CallToolResult, McpError> {
-        let roa_bytes = fs::read(path.0.path).into_mcp_error()?;
+        instrument("parse_roa_file", || async {
+            let roa_bytes = fs::read(path.0.path).into_mcp_error()?;
 
-        let roa: Roa = Roa::decode(roa_bytes.as_ref(), false).into_mcp_error()?;
+            let roa: Roa = Roa::decode(roa_bytes.as_ref(), false).into_mcp_error()?;
 
-        let roa_content = roa.content();
-        let asn = roa_content.as_id().to_string();
-        let v4_prefix: Vec<_> = roa_content
-            .v4_addrs()
-            .iter()
-            .map(|addr| addr.prefix().to_v4().to_string())
-            .collect();
+            let roa_content = roa.content();
+            let asn = roa_content.as_id().to_string();
+            let v4_prefix: Vec<_> = roa_content
+                .v4_addrs()
+                .iter()
+                .map(|addr| addr.prefix().to_v4().to_string())
+                .collect();
 
-        let v6_prefix: Vec<_> = roa_content
-            .v6_addrs()
-            .iter()
-            .map(|addr| addr.prefix().to_v6().to_string())
-            .collect();
+            let v6_prefix: Vec<_> = roa_content
+                .v6_addrs()
+                .iter()
+                .map(|addr| addr.prefix().to_v6().to_string())
+                .collect();
 
-        let parsed = ParsedRoa {
-            asn,
-            v4_prefix,
-            v6_prefix,
-        };
+            let parsed = ParsedRoa {
+                asn,
+                v4_prefix,
+                v6_prefix,
+            };
 
-        let json_value = RPKITool::to_json(parsed)?;
+            let json_value = RPKITool::to_json(parsed)?;
 
-        Ok(CallToolResult::structured(json_value))
+            Ok(CallToolResult::structured(json_value))
+        })
+        .await
     }
 }
 
@@ -376,6 +705,198 @@ impl From<String> for AppError {
     }
 }
 
+/// Which transport `main()` exposes the `RPKITool` handler over. `Stdio` is
+/// the default; `Sse`/`Http` bind a network listener instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Transport {
+    Stdio,
+    Sse,
+    Http,
+}
+
+impl Transport {
+    const DEFAULT_LISTEN_ADDR: &'static str = "127.0.0.1:8080";
+
+    fn parse(value: &str) -> Result<Self, String> {
+        match value.to_ascii_lowercase().as_str() {
+            "stdio" => Ok(Transport::Stdio),
+            "sse" => Ok(Transport::Sse),
+            "http" | "streamable-http" => Ok(Transport::Http),
+            other => Err(format!(
+                "Unknown transport '{other}', expected one of: stdio, sse, http"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum AuthConfig {
+    Bearer(String),
+    Header { name: String, value: String },
+}
+
+impl AuthConfig {
+    fn parse_header(raw: &str) -> Result<Self, String> {
+        let (name, value) = raw
+            .split_once(':')
+            .ok_or_else(|| format!("'{raw}' is not a valid header, expected Name:Value"))?;
+        Ok(AuthConfig::Header {
+            name: name.trim().to_string(),
+            value: value.trim().to_string(),
+        })
+    }
+}
+
+/// Default bind address for the `/metrics` HTTP endpoint. Deliberately
+/// distinct from [`Transport::DEFAULT_LISTEN_ADDR`] since the two servers
+/// are independent and may both be running at once.
+const DEFAULT_METRICS_LISTEN_ADDR: &str = "127.0.0.1:9898";
+
+/// Profile name used for a single endpoint synthesized from legacy CLI flags.
+const LEGACY_PROFILE_NAME: &str = "default";
+
+struct Cli {
+    endpoint: Option<String>,
+    config_path: Option<String>,
+    transport: Transport,
+    listen: String,
+    auth: Option<AuthConfig>,
+    metrics_listen: String,
+}
+
+fn parse_args(args: &[String]) -> Result<Cli, String> {
+    let mut endpoint = None;
+    let mut config_path = env::var("RPKI_MCP_CONFIG").ok();
+    let mut transport = match env::var("RPKI_MCP_TRANSPORT") {
+        Ok(value) => Transport::parse(&value)?,
+        Err(_) => Transport::Stdio,
+    };
+    let mut listen = env::var("RPKI_MCP_LISTEN")
+        .unwrap_or_else(|_| Transport::DEFAULT_LISTEN_ADDR.to_string());
+    let mut auth = match env::var("RPKI_MCP_AUTH_BEARER") {
+        Ok(token) => Some(AuthConfig::Bearer(token)),
+        Err(_) => match env::var("RPKI_MCP_AUTH_HEADER") {
+            Ok(header) => Some(AuthConfig::parse_header(&header)?),
+            Err(_) => None,
+        },
+    };
+    let mut metrics_listen = env::var("RPKI_MCP_METRICS_LISTEN")
+        .unwrap_or_else(|_| DEFAULT_METRICS_LISTEN_ADDR.to_string());
+
+    let mut rest = args.iter().skip(1);
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--config" => {
+                let value = rest
+                    .next()
+                    .ok_or_else(|| "--config requires a value".to_string())?;
+                config_path = Some(value.clone());
+            }
+            "--transport" => {
+                let value = rest
+                    .next()
+                    .ok_or_else(|| "--transport requires a value".to_string())?;
+                transport = Transport::parse(value)?;
+            }
+            "--listen" => {
+                let value = rest
+                    .next()
+                    .ok_or_else(|| "--listen requires a value".to_string())?;
+                listen = value.clone();
+            }
+            "--auth-bearer" => {
+                let value = rest
+                    .next()
+                    .ok_or_else(|| "--auth-bearer requires a value".to_string())?;
+                auth = Some(AuthConfig::Bearer(value.clone()));
+            }
+            "--auth-header" => {
+                let value = rest
+                    .next()
+                    .ok_or_else(|| "--auth-header requires a value".to_string())?;
+                auth = Some(AuthConfig::parse_header(value)?);
+            }
+            "--metrics-listen" => {
+                let value = rest
+                    .next()
+                    .ok_or_else(|| "--metrics-listen requires a value".to_string())?;
+                metrics_listen = value.clone();
+            }
+            other if endpoint.is_none() => endpoint = Some(other.to_string()),
+            other => return Err(format!("Unexpected argument: {other}")),
+        }
+    }
+
+    Ok(Cli {
+        endpoint,
+        config_path,
+        transport,
+        listen,
+        auth,
+        metrics_listen,
+    })
+}
+
+/// Resolves the effective [`config::FileConfig`]. An explicit `--config`/
+/// `RPKI_MCP_CONFIG` always wins. Otherwise, an explicit endpoint argument
+/// takes priority over a config file found at a conventional location (the
+/// user asked for this specific endpoint; an ambient `./rpki-mcp.toml`
+/// shouldn't silently override that), and we only fall back to ambient
+/// discovery when no endpoint was given either.
+fn resolve_config(cli: &Cli) -> Result<config::FileConfig, String> {
+    if let Some(path) = &cli.config_path {
+        let file_config = config::FileConfig::load(path)?;
+        if cli.endpoint.is_some() {
+            tracing::warn!(
+                "Both --config '{path}' and an explicit endpoint argument were given; using the config file and ignoring the endpoint"
+            );
+        }
+        if cli.auth.is_some() {
+            tracing::warn!(
+                "Both --config '{path}' and an explicit auth flag were given; using the config file's credentials and ignoring the auth flag"
+            );
+        }
+        return Ok(file_config);
+    }
+
+    let Some(endpoint) = cli.endpoint.clone() else {
+        return config::FileConfig::load_ambient()?.ok_or_else(|| {
+            "Missing required argument: endpoint URL (or use --config).".to_string()
+        });
+    };
+
+    if let Some(ambient_path) = config::FileConfig::ambient_path() {
+        tracing::warn!(
+            "Ignoring config file '{ambient_path}' found in a conventional location because an explicit endpoint argument was also given; pass --config to use it instead"
+        );
+    }
+
+    let mut profile = config::ProfileConfig::new(endpoint);
+    match &cli.auth {
+        Some(AuthConfig::Bearer(token)) => profile.bearer_token = Some(token.clone()),
+        Some(AuthConfig::Header { name, value }) => {
+            profile.header_name = Some(name.clone());
+            profile.header_value = Some(value.clone());
+        }
+        None => {}
+    }
+
+    Ok(config::FileConfig::single(
+        LEGACY_PROFILE_NAME.to_string(),
+        profile,
+    ))
+}
+
+/// Serves the Prometheus `/metrics` endpoint until the process exits. Runs
+/// as its own background task, independent of whichever MCP transport was
+/// selected.
+async fn serve_metrics(addr: std::net::SocketAddr) -> std::io::Result<()> {
+    let router =
+        axum::Router::new().route("/metrics", axum::routing::get(|| async { metrics::render() }));
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    axum::serve(listener, router).await
+}
+
 #[tokio::main]
 #[allow(clippy::result_large_err)]
 async fn main() -> Result<(), AppError> {
@@ -394,21 +915,165 @@ async fn main() -> Result<(), AppError> {
         .init();
 
     let args: Vec<String> = env::args().collect();
+    let cli = parse_args(&args).map_err(|err| {
+        tracing::error!("{}", &err);
+        AppError::Input(err)
+    })?;
 
-    if args.len() == 1 {
-        let err_msg = "Missing required argument: endpoint URL.";
-        tracing::error!("{}", &err_msg);
-        return Err(AppError::Input(err_msg.to_string()));
-    }
+    let file_config = resolve_config(&cli)?;
+    let tool = RPKITool::new(file_config)?;
 
-    let endpoint = args[1].clone();
-    let service = RPKITool::new(endpoint)?
-        .serve(stdio())
-        .await
-        .inspect_err(|e| {
-            tracing::error!("Error starting server: {e}");
-        })?;
-    service.waiting().await?;
+    let metrics_addr: std::net::SocketAddr = cli
+        .metrics_listen
+        .parse()
+        .map_err(|err| AppError::Input(format!("Invalid metrics listen address: {err}")))?;
+    tokio::spawn(async move {
+        if let Err(err) = serve_metrics(metrics_addr).await {
+            tracing::error!("Metrics server on {metrics_addr} failed: {err}");
+        }
+    });
+
+    match cli.transport {
+        Transport::Stdio => {
+            let service = tool.serve(stdio()).await.inspect_err(|e| {
+                tracing::error!("Error starting server: {e}");
+            })?;
+            service.waiting().await?;
+        }
+        Transport::Sse => {
+            let addr = cli
+                .listen
+                .parse()
+                .map_err(|err| AppError::Input(format!("Invalid listen address: {err}")))?;
+            let ct = SseServer::serve(addr)
+                .await
+                .inspect_err(|e| {
+                    tracing::error!("Error starting SSE server: {e}");
+                })?
+                .with_service(move || tool.clone());
+            tracing::info!("Serving RPKITool over SSE on {addr}");
+            tokio::signal::ctrl_c().await?;
+            ct.cancel();
+        }
+        Transport::Http => {
+            let addr = cli
+                .listen
+                .parse()
+                .map_err(|err| AppError::Input(format!("Invalid listen address: {err}")))?;
+            let http_service = StreamableHttpService::new(
+                move || Ok(tool.clone()),
+                LocalSessionManager::default().into(),
+                Default::default(),
+            );
+            let router = axum::Router::new().nest_service("/mcp", http_service);
+            let listener = tokio::net::TcpListener::bind(addr).await?;
+            tracing::info!("Serving RPKITool over streamable HTTP on {addr}");
+            axum::serve(listener, router)
+                .with_graceful_shutdown(async {
+                    let _ = tokio::signal::ctrl_c().await;
+                })
+                .await?;
+        }
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response_with_header(name: reqwest::header::HeaderName, value: &str) -> reqwest::Response {
+        http::Response::builder()
+            .header(name, value)
+            .body(reqwest::Body::from(Vec::<u8>::new()))
+            .unwrap()
+            .into()
+    }
+
+    fn response_without_headers() -> reqwest::Response {
+        http::Response::builder()
+            .body(reqwest::Body::from(Vec::<u8>::new()))
+            .unwrap()
+            .into()
+    }
+
+    #[test]
+    fn retry_after_delay_parses_a_present_header() {
+        let response = response_with_header(reqwest::header::RETRY_AFTER, "5");
+        assert_eq!(retry_after_delay(&response), Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn retry_after_delay_ignores_a_missing_header() {
+        assert_eq!(retry_after_delay(&response_without_headers()), None);
+    }
+
+    #[test]
+    fn retry_after_delay_ignores_a_non_numeric_header() {
+        let response = response_with_header(reqwest::header::RETRY_AFTER, "Wed, 21 Oct 2026 07:28:00 GMT");
+        assert_eq!(retry_after_delay(&response), None);
+    }
+
+    #[test]
+    fn backoff_delay_grows_exponentially_before_the_cap() {
+        let first = backoff_delay(1);
+        assert!(first >= BASE_RETRY_DELAY);
+        assert!(first < BASE_RETRY_DELAY + Duration::from_millis(50));
+
+        let second = backoff_delay(2);
+        assert!(second >= BASE_RETRY_DELAY * 2);
+        assert!(second < BASE_RETRY_DELAY * 2 + Duration::from_millis(50));
+    }
+
+    #[test]
+    fn backoff_delay_caps_at_max_retry_delay() {
+        let capped = backoff_delay(100);
+        assert!(capped >= MAX_RETRY_DELAY);
+        assert!(capped < MAX_RETRY_DELAY + Duration::from_millis(50));
+    }
+
+    #[test]
+    fn parse_asn_accepts_as_prefix_and_bare_digits() {
+        assert_eq!(parse_asn("AS64496").unwrap(), 64496);
+        assert_eq!(parse_asn("as64496").unwrap(), 64496);
+        assert_eq!(parse_asn("64496").unwrap(), 64496);
+    }
+
+    #[test]
+    fn parse_asn_rejects_garbage() {
+        assert!(parse_asn("not-an-asn").is_err());
+        assert!(parse_asn("AS").is_err());
+        assert!(parse_asn("").is_err());
+    }
+
+    #[test]
+    fn parse_prefix_accepts_v4_and_v6() {
+        let (addr, len) = parse_prefix("192.0.2.0/24").unwrap();
+        assert!(addr.is_ipv4());
+        assert_eq!(len, 24);
+
+        let (addr, len) = parse_prefix("2001:db8::/32").unwrap();
+        assert!(addr.is_ipv6());
+        assert_eq!(len, 32);
+    }
+
+    #[test]
+    fn parse_prefix_accepts_max_length_boundary() {
+        assert!(parse_prefix("192.0.2.1/32").is_ok());
+        assert!(parse_prefix("::1/128").is_ok());
+    }
+
+    #[test]
+    fn parse_prefix_rejects_length_past_family_max() {
+        assert!(parse_prefix("192.0.2.0/33").is_err());
+        assert!(parse_prefix("::/129").is_err());
+    }
+
+    #[test]
+    fn parse_prefix_rejects_malformed_input() {
+        assert!(parse_prefix("not-a-prefix").is_err());
+        assert!(parse_prefix("192.0.2.0/abc").is_err());
+        assert!(parse_prefix("192.0.2.0").is_err());
+    }
+}
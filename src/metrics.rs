@@ -0,0 +1,81 @@
+//! Prometheus metrics for the MCP tool surface, exposed over `/metrics`.
+
+use prometheus::{Encoder, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder};
+use std::sync::LazyLock;
+use std::time::Duration;
+
+static REGISTRY: LazyLock<Registry> = LazyLock::new(Registry::new);
+
+static REQUESTS_TOTAL: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    let counter = IntCounterVec::new(
+        Opts::new(
+            "rpki_mcp_tool_requests_total",
+            "Total number of tool invocations, by tool",
+        ),
+        &["tool"],
+    )
+    .expect("metric name and labels are valid");
+    REGISTRY
+        .register(Box::new(counter.clone()))
+        .expect("metric is only registered once");
+    counter
+});
+
+static ERRORS_TOTAL: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    let counter = IntCounterVec::new(
+        Opts::new(
+            "rpki_mcp_tool_errors_total",
+            "Total number of tool failures, by tool and JSON-RPC error code",
+        ),
+        &["tool", "code"],
+    )
+    .expect("metric name and labels are valid");
+    REGISTRY
+        .register(Box::new(counter.clone()))
+        .expect("metric is only registered once");
+    counter
+});
+
+static LATENCY_SECONDS: LazyLock<HistogramVec> = LazyLock::new(|| {
+    let histogram = HistogramVec::new(
+        prometheus::HistogramOpts::new(
+            "rpki_mcp_tool_latency_seconds",
+            "Tool call latency in seconds, by tool",
+        ),
+        &["tool"],
+    )
+    .expect("metric name and labels are valid");
+    REGISTRY
+        .register(Box::new(histogram.clone()))
+        .expect("metric is only registered once");
+    histogram
+});
+
+/// Records that `tool` was invoked.
+pub fn record_request(tool: &str) {
+    REQUESTS_TOTAL.with_label_values(&[tool]).inc();
+}
+
+/// Records how long a call to `tool` took.
+pub fn record_latency(tool: &str, elapsed: Duration) {
+    LATENCY_SECONDS
+        .with_label_values(&[tool])
+        .observe(elapsed.as_secs_f64());
+}
+
+/// Records that a call to `tool` failed with the given JSON-RPC error code.
+pub fn record_error(tool: &str, code: i32) {
+    ERRORS_TOTAL
+        .with_label_values(&[tool, &code.to_string()])
+        .inc();
+}
+
+/// Renders all registered metrics in Prometheus text exposition format.
+pub fn render() -> String {
+    let metric_families = REGISTRY.gather();
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&metric_families, &mut buffer)
+        .expect("encoding to an in-memory buffer cannot fail");
+    String::from_utf8(buffer).expect("Prometheus text encoding is always valid UTF-8")
+}
@@ -0,0 +1,426 @@
+//! Offline VRP-set validation: load a Validated ROA Payload (VRP) dump from
+//! a local file and answer `validity`-shaped queries against it without a
+//! relying-party round trip.
+
+use crate::{Route, ValidatedRoute, Validity, ValidityResponse, Vrp, VRPs};
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+/// One VRP from the loaded dump, kept in display form alongside the bits
+/// needed for longest-prefix-match lookups.
+#[derive(Debug, Clone)]
+struct VrpEntry {
+    asn: u32,
+    asn_display: String,
+    prefix_display: String,
+    prefix_len: u8,
+    max_length: u8,
+}
+
+/// A longest-prefix-match index for one address family: VRPs bucketed by
+/// prefix length, then by the address truncated to that length, so covering
+/// lookups are a bucket hit per candidate length instead of a linear scan.
+#[derive(Debug, Default)]
+struct LpmTree<A> {
+    by_len: HashMap<u8, HashMap<A, Vec<VrpEntry>>>,
+}
+
+impl<A: Eq + std::hash::Hash + Copy> LpmTree<A> {
+    fn insert(&mut self, len: u8, key: A, entry: VrpEntry) {
+        self.by_len
+            .entry(len)
+            .or_default()
+            .entry(key)
+            .or_default()
+            .push(entry);
+    }
+
+    /// Returns every VRP whose prefix covers the query address, i.e. every
+    /// VRP at length `len <= query_len` whose network address matches the
+    /// query address truncated to `len` bits.
+    fn covering(&self, query_len: u8, truncate: impl Fn(u8) -> A) -> Vec<&VrpEntry> {
+        let mut out = Vec::new();
+        for len in 0..=query_len {
+            if let Some(bucket) = self.by_len.get(&len) {
+                if let Some(entries) = bucket.get(&truncate(len)) {
+                    out.extend(entries.iter());
+                }
+            }
+        }
+        out
+    }
+}
+
+fn truncate_v4(addr: u32, len: u8) -> u32 {
+    if len == 0 {
+        0
+    } else {
+        addr & (u32::MAX << (32 - len))
+    }
+}
+
+fn truncate_v6(addr: u128, len: u8) -> u128 {
+    if len == 0 {
+        0
+    } else {
+        addr & (u128::MAX << (128 - len))
+    }
+}
+
+/// In-memory VRP set loaded from a file, indexed for repeated lookups.
+#[derive(Debug, Default)]
+pub struct VrpIndex {
+    v4: LpmTree<u32>,
+    v6: LpmTree<u128>,
+}
+
+/// One row of a VRP dump, before it's parsed into address-family bits.
+struct RawVrp {
+    asn: String,
+    prefix: String,
+    max_length: i64,
+}
+
+impl VrpIndex {
+    /// Parses a VRP dump (JSON or CSV) and builds an index over it.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path).map_err(|err| {
+            format!("Failed to read VRP file '{path}': {err}")
+        })?;
+
+        let raw = if path.ends_with(".json") {
+            Self::parse_json(&contents)?
+        } else {
+            Self::parse_csv(&contents)?
+        };
+
+        let mut index = VrpIndex::default();
+        for entry in raw {
+            index.insert(entry)?;
+        }
+        Ok(index)
+    }
+
+    /// The standard relying-party JSON export, parsed as [`crate::FetchedRoas`]:
+    /// `{"metadata": {"generated", "generatedTime"}, "roas": [{"asn", "prefix", "maxLength", "ta"}, ...]}`.
+    /// All of these fields must be present.
+    fn parse_json(contents: &str) -> Result<Vec<RawVrp>, String> {
+        let parsed: crate::FetchedRoas =
+            serde_json::from_str(contents).map_err(|err| format!("Invalid VRP JSON: {err}"))?;
+        Ok(parsed
+            .roas
+            .into_iter()
+            .map(|roa| RawVrp {
+                asn: roa.asn,
+                prefix: roa.prefix,
+                max_length: roa.max_length,
+            })
+            .collect())
+    }
+
+    /// `ASN,Prefix,Max Length` CSV, with an optional header row (skipped if
+    /// the first field isn't a valid ASN).
+    fn parse_csv(contents: &str) -> Result<Vec<RawVrp>, String> {
+        let mut rows = Vec::new();
+        for (line_no, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            if fields.len() != 3 {
+                return Err(format!(
+                    "Line {}: expected 3 comma-separated fields (ASN,Prefix,MaxLength), got {}",
+                    line_no + 1,
+                    fields.len()
+                ));
+            }
+            if line_no == 0 && crate::parse_asn(fields[0]).is_err() {
+                continue; // header row
+            }
+            let max_length: i64 = fields[2]
+                .parse()
+                .map_err(|_| format!("Line {}: invalid max length '{}'", line_no + 1, fields[2]))?;
+            rows.push(RawVrp {
+                asn: fields[0].to_string(),
+                prefix: fields[1].to_string(),
+                max_length,
+            });
+        }
+        Ok(rows)
+    }
+
+    fn insert(&mut self, raw: RawVrp) -> Result<(), String> {
+        let asn = crate::parse_asn(&raw.asn)?;
+        let (addr, prefix_len) = crate::parse_prefix(&raw.prefix)?;
+        let family_max = if addr.is_ipv4() { 32 } else { 128 };
+        let max_length = u8::try_from(raw.max_length)
+            .ok()
+            .filter(|&len| len <= family_max)
+            .ok_or_else(|| {
+                format!(
+                    "'{}': max length {} is not in range 0..={family_max}",
+                    raw.prefix, raw.max_length
+                )
+            })?;
+        let entry = VrpEntry {
+            asn,
+            asn_display: raw.asn,
+            prefix_display: raw.prefix,
+            prefix_len,
+            max_length,
+        };
+        match addr {
+            IpAddr::V4(addr) => {
+                let key = truncate_v4(u32::from(addr), prefix_len);
+                self.v4.insert(prefix_len, key, entry);
+            }
+            IpAddr::V6(addr) => {
+                let key = truncate_v6(u128::from(addr), prefix_len);
+                self.v6.insert(prefix_len, key, entry);
+            }
+        }
+        Ok(())
+    }
+
+    /// Answers a `validity`-shaped query against this index; same response
+    /// shape as the remote `validity` tool.
+    pub fn query(&self, asn_raw: &str, prefix_raw: &str) -> Result<ValidityResponse, String> {
+        let query_asn = crate::parse_asn(asn_raw)?;
+        let (query_addr, query_len) = crate::parse_prefix(prefix_raw)?;
+
+        let covering: Vec<&VrpEntry> = match query_addr {
+            IpAddr::V4(addr) => {
+                let bits = u32::from(addr);
+                self.v4.covering(query_len, |len| truncate_v4(bits, len))
+            }
+            IpAddr::V6(addr) => {
+                let bits = u128::from(addr);
+                self.v6.covering(query_len, |len| truncate_v6(bits, len))
+            }
+        };
+
+        let to_vrp = |entry: &VrpEntry| Vrp {
+            asn: entry.asn_display.clone(),
+            prefix: entry.prefix_display.clone(),
+            max_length: entry.max_length.to_string(),
+        };
+
+        let (state, description) = if covering.is_empty() {
+            ("not-found", "No covering VRPs found for this prefix")
+        } else if covering
+            .iter()
+            .any(|e| e.asn == query_asn && e.prefix_len <= query_len && query_len <= e.max_length)
+        {
+            ("valid", "Matched by a covering VRP")
+        } else {
+            (
+                "invalid",
+                "Covering VRPs exist but none match both origin ASN and prefix length",
+            )
+        };
+
+        let matched: Vec<Vrp> = covering
+            .iter()
+            .filter(|e| e.asn == query_asn && e.prefix_len <= query_len && query_len <= e.max_length)
+            .map(|e| to_vrp(e))
+            .collect();
+        let unmatched_as: Vec<Vrp> = covering
+            .iter()
+            .filter(|e| e.asn != query_asn)
+            .map(|e| to_vrp(e))
+            .collect();
+        let unmatched_length: Vec<Vrp> = covering
+            .iter()
+            .filter(|e| e.asn == query_asn && query_len > e.max_length)
+            .map(|e| to_vrp(e))
+            .collect();
+
+        Ok(ValidityResponse {
+            validated_route: ValidatedRoute {
+                route: Route {
+                    origin_asn: asn_raw.to_string(),
+                    prefix: prefix_raw.to_string(),
+                },
+                validity: Validity {
+                    state: state.to_string(),
+                    description: description.to_string(),
+                    vrps: VRPs {
+                        matched,
+                        unmatched_as,
+                        unmatched_length,
+                    },
+                },
+            },
+            generated_time: chrono::Utc::now().to_rfc3339(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn index_with(entries: &[(&str, &str, i64)]) -> VrpIndex {
+        let mut index = VrpIndex::default();
+        for (asn, prefix, max_length) in entries {
+            index
+                .insert(RawVrp {
+                    asn: asn.to_string(),
+                    prefix: prefix.to_string(),
+                    max_length: *max_length,
+                })
+                .expect("valid VRP");
+        }
+        index
+    }
+
+    #[test]
+    fn insert_rejects_negative_max_length() {
+        let mut index = VrpIndex::default();
+        let err = index
+            .insert(RawVrp {
+                asn: "AS64496".to_string(),
+                prefix: "192.0.2.0/24".to_string(),
+                max_length: -1,
+            })
+            .unwrap_err();
+        assert!(err.contains("max length"));
+    }
+
+    #[test]
+    fn insert_rejects_max_length_past_family_max() {
+        let mut index = VrpIndex::default();
+        let err = index
+            .insert(RawVrp {
+                asn: "AS64496".to_string(),
+                prefix: "192.0.2.0/24".to_string(),
+                max_length: 300,
+            })
+            .unwrap_err();
+        assert!(err.contains("max length"));
+
+        let mut index = VrpIndex::default();
+        let err = index
+            .insert(RawVrp {
+                asn: "AS64496".to_string(),
+                prefix: "2001:db8::/32".to_string(),
+                max_length: 129,
+            })
+            .unwrap_err();
+        assert!(err.contains("max length"));
+    }
+
+    #[test]
+    fn insert_accepts_max_length_at_family_boundary() {
+        let mut index = VrpIndex::default();
+        index
+            .insert(RawVrp {
+                asn: "AS64496".to_string(),
+                prefix: "192.0.2.0/24".to_string(),
+                max_length: 32,
+            })
+            .expect("32 is a valid IPv4 max length");
+
+        let mut index = VrpIndex::default();
+        index
+            .insert(RawVrp {
+                asn: "AS64496".to_string(),
+                prefix: "2001:db8::/32".to_string(),
+                max_length: 128,
+            })
+            .expect("128 is a valid IPv6 max length");
+    }
+
+    #[test]
+    fn v4_exact_match_is_valid() {
+        let index = index_with(&[("AS64496", "192.0.2.0/24", 24)]);
+        let response = index.query("AS64496", "192.0.2.0/24").unwrap();
+        assert_eq!(response.validated_route.validity.state, "valid");
+        assert_eq!(response.validated_route.validity.vrps.matched.len(), 1);
+    }
+
+    #[test]
+    fn v4_covering_but_wrong_asn_is_invalid() {
+        let index = index_with(&[("AS64496", "192.0.2.0/24", 24)]);
+        let response = index.query("AS64497", "192.0.2.0/24").unwrap();
+        assert_eq!(response.validated_route.validity.state, "invalid");
+        assert!(response.validated_route.validity.vrps.matched.is_empty());
+        assert_eq!(response.validated_route.validity.vrps.unmatched_as.len(), 1);
+        assert!(response.validated_route.validity.vrps.unmatched_length.is_empty());
+    }
+
+    #[test]
+    fn v4_covering_but_too_long_is_invalid() {
+        let index = index_with(&[("AS64496", "192.0.2.0/24", 24)]);
+        let response = index.query("AS64496", "192.0.2.128/25").unwrap();
+        assert_eq!(response.validated_route.validity.state, "invalid");
+        assert!(response.validated_route.validity.vrps.matched.is_empty());
+        assert!(response.validated_route.validity.vrps.unmatched_as.is_empty());
+        assert_eq!(response.validated_route.validity.vrps.unmatched_length.len(), 1);
+    }
+
+    #[test]
+    fn v4_max_length_boundary_is_valid() {
+        let index = index_with(&[("AS64496", "192.0.2.0/24", 25)]);
+        let response = index.query("AS64496", "192.0.2.128/25").unwrap();
+        assert_eq!(response.validated_route.validity.state, "valid");
+    }
+
+    #[test]
+    fn v4_no_covering_vrp_is_not_found() {
+        let index = index_with(&[("AS64496", "192.0.2.0/24", 24)]);
+        let response = index.query("AS64496", "203.0.113.0/24").unwrap();
+        assert_eq!(response.validated_route.validity.state, "not-found");
+    }
+
+    #[test]
+    fn v4_default_route_vrp_covers_any_prefix() {
+        let index = index_with(&[("AS64496", "0.0.0.0/0", 32)]);
+        let response = index.query("AS64496", "192.0.2.0/24").unwrap();
+        assert_eq!(response.validated_route.validity.state, "valid");
+    }
+
+    #[test]
+    fn v6_exact_match_is_valid() {
+        let index = index_with(&[("AS64496", "2001:db8::/32", 32)]);
+        let response = index.query("AS64496", "2001:db8::/32").unwrap();
+        assert_eq!(response.validated_route.validity.state, "valid");
+    }
+
+    #[test]
+    fn v6_host_route_max_length_boundary_is_valid() {
+        let index = index_with(&[("AS64496", "2001:db8::/32", 128)]);
+        let response = index.query("AS64496", "2001:db8::1/128").unwrap();
+        assert_eq!(response.validated_route.validity.state, "valid");
+    }
+
+    #[test]
+    fn csv_with_header_row_is_skipped() {
+        let csv = "ASN,Prefix,Max Length\nAS64496,192.0.2.0/24,24\n";
+        let rows = VrpIndex::parse_csv(csv).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].asn, "AS64496");
+    }
+
+    #[test]
+    fn csv_without_header_row_keeps_first_row() {
+        let csv = "AS64496,192.0.2.0/24,24\nAS64497,198.51.100.0/24,24\n";
+        let rows = VrpIndex::parse_csv(csv).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].asn, "AS64496");
+        assert_eq!(rows[1].asn, "AS64497");
+    }
+
+    #[test]
+    fn json_dump_is_parsed_into_rows() {
+        let json = r#"{
+            "metadata": {"generated": 1, "generatedTime": "2024-01-01T00:00:00Z"},
+            "roas": [{"asn": "AS64496", "prefix": "192.0.2.0/24", "maxLength": 24, "ta": "test"}]
+        }"#;
+        let rows = VrpIndex::parse_json(json).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].asn, "AS64496");
+        assert_eq!(rows[0].max_length, 24);
+    }
+}